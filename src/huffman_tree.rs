@@ -1,142 +1,642 @@
-use std::rc::Rc;
-use std::cmp::{Ordering, Reverse};
-use std::collections::{HashMap, BinaryHeap};
-
-/// Denotes a Huffman node as either an internal node (if it has children)
-/// or a leaf node (if it stores a character).
-enum NodeData {
-    Children(Rc<HuffmanNode>, Rc<HuffmanNode>),
-    Character(char),
-}
-
-/// A node in the Huffman code tree.
-/// All nodes are associated with a frequency, which is used by the algorithm
-/// to construct the code tree by merging by lowest frequency.
-struct HuffmanNode {
-    freq: usize,
-    data: NodeData,
-}
-
-impl HuffmanNode {
-    fn leaf(c: char, freq: usize) -> Self {
-        Self { freq, data: NodeData::Character(c) }
-    }
-
-    fn internal(left: Rc<HuffmanNode>, right: Rc<HuffmanNode>, freq: usize) -> Self {
-        Self { freq, data: NodeData::Children(left, right) }
-    }
-
-    fn freq(&self) -> usize {
-        self.freq
-    }
-}
-
-impl Eq for HuffmanNode {}
-
-impl PartialEq for HuffmanNode {
-    fn eq(&self, other: &Self) -> bool {
-        self.freq == other.freq
-    }
-}
-
-impl PartialOrd for HuffmanNode {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
-}
-
-/// Orders nodes in reverse order of frequency so that nodes with least
-/// frequency have highest priority in the priority queue.
-/// In other words, the BinaryHeap into which they are inserted becomes a 
-/// MIN priority queue.
-impl Ord for HuffmanNode {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.freq.cmp(&other.freq).reverse()
-    }
-}
-
-/// Constructs a min priority queue of leaf nodes in the Huffamn code tree.
-/// Prepares the portion of the algorithm which repeatedly merges the nodes
-/// with the smallest frequencies.
-fn get_frequencies(s: &str) -> BinaryHeap<HuffmanNode> {
-    let mut freq = HashMap::new();
-    for c in s.chars() {
-        let entry = freq.entry(c).or_insert(0);
-        *entry += 1;
-    }
-
-    let mut min_queue = BinaryHeap::new();
-    for c in freq.keys() {
-        min_queue.push(HuffmanNode::leaf(*c, freq[c]));
-    }
-    min_queue
-}
-
-
-
-/// Constructs a Huffman code tree and returns the root node.
-fn build_huffman_tree(min_queue: &mut BinaryHeap<HuffmanNode>) 
-    -> Result<HuffmanNode, &'static str> 
-{
-    if min_queue.is_empty() {
-        return Err("cannot construct a Huffman code with no characters");
-    }
-
-    while min_queue.len() > 1 {
-        let x = min_queue.pop().unwrap();
-        let y = min_queue.pop().unwrap();
-        let freq_sum = x.freq() + y.freq();
-        let z = HuffmanNode::internal(Rc::new(x), Rc::new(y), freq_sum);
-        min_queue.push(z);
-    }
-    Ok(min_queue.pop().unwrap())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn tree_built_correctly() {
-        let s = "aaaabbbccd";
-        let mut freq = get_frequencies(&s);
-        
-        assert!(matches!(freq.pop().unwrap().data, NodeData::Character('d')));
-        assert!(matches!(freq.pop().unwrap().data, NodeData::Character('c')));
-        assert!(matches!(freq.pop().unwrap().data, NodeData::Character('b')));
-        assert!(matches!(freq.pop().unwrap().data, NodeData::Character('a')));
-        assert!(matches!(freq.pop(), None));
-
-        freq = get_frequencies(&s);
-        let root = build_huffman_tree(&mut freq).unwrap();
-        assert_eq!(root.freq, 10);
-        match root.data {
-            NodeData::Children(left, right) => {
-                assert_eq!(left.freq, 4);
-                assert_eq!(right.freq, 6);
-
-                assert!(matches!(&left.data, NodeData::Character('a')));
-                match &right.data {
-                    NodeData::Children(left, right) => {
-                        assert_eq!(left.freq, 3);
-                        assert_eq!(right.freq, 3);
-
-                        assert!(matches!(&left.data, NodeData::Character('b')));
-                        match &right.data {
-                            NodeData::Children(left, right) => {
-                                assert_eq!(left.freq, 1);
-                                assert_eq!(right.freq, 2);
-
-                                assert!(matches!(left.data, NodeData::Character('d')));
-                                assert!(matches!(right.data, NodeData::Character('c')));
-                            },
-                            _ => panic!("node should have children")
-                        }
-                    },
-                    _ => panic!("node should have children")
-                }
-            },
-            _ => panic!("node should have children")
-        }
-    }
-}
+use std::cmp::Reverse;
+use std::collections::{HashMap, BinaryHeap};
+use std::fmt;
+
+use bit_vec::BitVec;
+
+/// Size of the fixed frequency table: one slot per possible byte value.
+/// Bounding symbols to `u8` means the tree never holds more than
+/// `2 * MAX_SYMBOLS - 1` nodes, so the arena can be preallocated.
+const MAX_SYMBOLS: usize = 256;
+
+/// A node in the Huffman code tree, stored by index in a flat arena
+/// instead of linked through `Rc` pointers. This keeps the tree
+/// contiguous in memory and lets a node be copied freely rather than
+/// refcounted; `build_huffman_tree` and `tree_from_codes` both build one
+/// of these arenas, and decoding walks it via `left`/`right` indices.
+///
+/// Merge weights live only in `build_huffman_tree`'s priority queue
+/// (as `(count, node index)` pairs) long enough to pick merge order;
+/// nothing downstream of the tree needs a node's weight, so it isn't
+/// stored here.
+#[derive(Clone, Copy)]
+struct Node {
+    symbol: Option<u8>,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+impl Node {
+    fn leaf(symbol: u8) -> Self {
+        Self { symbol: Some(symbol), left: None, right: None }
+    }
+
+    fn internal(left: usize, right: usize) -> Self {
+        Self { symbol: None, left: Some(left), right: Some(right) }
+    }
+
+    fn empty() -> Self {
+        Self { symbol: None, left: None, right: None }
+    }
+}
+
+/// Counts byte frequencies in `bytes` into a fixed-size table, to be fed
+/// into `build_huffman_tree`. A `[usize; MAX_SYMBOLS]` array indexed by
+/// byte value avoids hashing overhead entirely.
+fn get_frequencies(bytes: &[u8]) -> [usize; MAX_SYMBOLS] {
+    let mut freq = [0usize; MAX_SYMBOLS];
+    for &b in bytes {
+        freq[b as usize] += 1;
+    }
+    freq
+}
+
+/// Constructs a Huffman code tree as a flat arena and returns it along
+/// with the index of its root. The arena holds at most
+/// `2 * MAX_SYMBOLS - 1` nodes: one leaf per distinct byte plus one
+/// internal node per merge.
+///
+/// A `BinaryHeap<Reverse<(count, node index)>>` serves as the min
+/// priority queue, so ties in `count` always break on node index —
+/// leaves are pushed in ascending byte-value order and internal nodes
+/// are pushed in merge order, so the same frequencies always merge in
+/// the same order. This tree is only used to measure code *lengths*
+/// (see `code_lengths`); `canonical_codes` then reassigns the actual
+/// bits, which keeps the final output deterministic even on input where
+/// the tie-break happened to matter.
+fn build_huffman_tree(freq: &[usize; MAX_SYMBOLS]) -> Result<(Vec<Node>, usize), &'static str> {
+    let mut nodes = Vec::with_capacity(2 * MAX_SYMBOLS - 1);
+    let mut min_queue = BinaryHeap::new();
+    for (b, &count) in freq.iter().enumerate() {
+        if count > 0 {
+            nodes.push(Node::leaf(b as u8));
+            min_queue.push(Reverse((count, nodes.len() - 1)));
+        }
+    }
+
+    if min_queue.is_empty() {
+        return Err("cannot construct a Huffman code with no bytes");
+    }
+
+    while min_queue.len() > 1 {
+        let Reverse((x_count, x)) = min_queue.pop().unwrap();
+        let Reverse((y_count, y)) = min_queue.pop().unwrap();
+
+        let z = nodes.len();
+        nodes.push(Node::internal(x, y));
+
+        min_queue.push(Reverse((x_count + y_count, z)));
+    }
+
+    let Reverse((_, root)) = min_queue.pop().unwrap();
+    Ok((nodes, root))
+}
+
+/// Measures each symbol's depth in `nodes` (rooted at `root`), i.e. its
+/// Huffman code length. A tree with a single leaf has that leaf at the
+/// root with depth 0, but it still needs a 1-bit code, so that case is
+/// special-cased to length 1.
+fn code_lengths(nodes: &[Node], root: usize) -> [u8; MAX_SYMBOLS] {
+    let mut lengths = [0u8; MAX_SYMBOLS];
+
+    if let Some(b) = nodes[root].symbol {
+        lengths[b as usize] = 1;
+        return lengths;
+    }
+
+    let mut stack = vec![(root, 0u8)];
+    while let Some((i, depth)) = stack.pop() {
+        let node = &nodes[i];
+        if let Some(b) = node.symbol {
+            lengths[b as usize] = depth;
+        } else {
+            stack.push((node.left.unwrap(), depth + 1));
+            stack.push((node.right.unwrap(), depth + 1));
+        }
+    }
+
+    lengths
+}
+
+/// Reassigns codes canonically from per-symbol code `lengths`,
+/// discarding the shape of the tree that produced them: symbols are
+/// ordered by `(length, symbol value)` and assigned consecutive codes,
+/// with the first code at each length one more than the last code at
+/// the previous length, left-shifted by however much the length grew.
+/// Two trees with the same length distribution — however ties were
+/// broken while merging — always produce the same canonical codes,
+/// which keeps compressed output byte-identical across runs.
+///
+/// The running code is built up as a `BitVec` rather than a fixed-width
+/// integer: a skewed but entirely realistic frequency distribution
+/// (e.g. the classic Fibonacci-weighted worst case) can legitimately
+/// produce code lengths past 32 bits, which would overflow a `u32` shift.
+fn canonical_codes(lengths: &[u8; MAX_SYMBOLS]) -> HashMap<u8, BitVec> {
+    let mut symbols: Vec<(u8, u8)> = (0..MAX_SYMBOLS)
+        .filter(|&b| lengths[b] > 0)
+        .map(|b| (lengths[b], b as u8))
+        .collect();
+    symbols.sort_unstable();
+
+    let mut table = HashMap::new();
+    let mut code = BitVec::new();
+    let mut prev_len = 0u8;
+    for (len, symbol) in symbols {
+        for _ in 0..(len - prev_len) {
+            code.push(false);
+        }
+        prev_len = len;
+        table.insert(symbol, code.clone());
+        increment(&mut code);
+    }
+    table
+}
+
+/// Increments `bits`, read most-significant-bit first, as a binary
+/// counter in place (e.g. `011` becomes `100`). Used to step from one
+/// canonical code to the next without ever widening past the number of
+/// bits the code actually needs.
+fn increment(bits: &mut BitVec) {
+    for i in (0..bits.len()).rev() {
+        if !bits[i] {
+            bits.set(i, true);
+            return;
+        }
+        bits.set(i, false);
+    }
+}
+
+/// Rebuilds a navigable tree from a canonical code table, purely so
+/// `decode_bits` can walk it bit-by-bit the same way it would walk a
+/// merge-order tree. `table` is assumed to be prefix-free, which
+/// `canonical_codes` guarantees.
+fn tree_from_codes(table: &HashMap<u8, BitVec>) -> (Vec<Node>, usize) {
+    if table.len() == 1 {
+        let &symbol = table.keys().next().unwrap();
+        return (vec![Node::leaf(symbol)], 0);
+    }
+
+    let mut nodes = vec![Node::empty()];
+    let root = 0;
+    for (&symbol, code) in table {
+        let mut i = root;
+        let last = code.len() - 1;
+        for (pos, bit) in code.iter().enumerate() {
+            let existing = if bit { nodes[i].right } else { nodes[i].left };
+            let child = match existing {
+                Some(c) => c,
+                None => {
+                    let c = nodes.len();
+                    nodes.push(if pos == last { Node::leaf(symbol) } else { Node::empty() });
+                    if bit {
+                        nodes[i].right = Some(c);
+                    } else {
+                        nodes[i].left = Some(c);
+                    }
+                    c
+                }
+            };
+            i = child;
+        }
+    }
+    (nodes, root)
+}
+
+/// Everything needed to decode a Huffman-encoded bit stream: the
+/// canonical per-symbol code lengths (0 meaning the symbol is unused),
+/// plus the number of bytes the stream decodes to, so that padding bits
+/// in the final byte of a packed stream are never mistaken for
+/// additional output.
+///
+/// `to_bytes`/`from_bytes` serialize just the code-length table, which
+/// is all a decoder needs to reconstruct the canonical tree; the
+/// decoded length travels separately in `compress_to_bytes`'s stream
+/// format since it isn't part of the code itself.
+pub struct Header {
+    lengths: [u8; MAX_SYMBOLS],
+    len: usize,
+}
+
+impl Header {
+    /// Serializes the code-length table as one byte per possible symbol
+    /// value (0 meaning unused), positionally encoding the symbol set.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.lengths.to_vec()
+    }
+
+    /// Reverses `to_bytes`. `len` is the decoded byte length, which
+    /// `to_bytes` doesn't carry and so must be supplied by the caller
+    /// (see `compress_to_bytes`, which stores it alongside the header).
+    ///
+    /// Validates the length table before trusting it: a corrupted byte
+    /// could otherwise reach `canonical_codes`/`tree_from_codes` and
+    /// either panic or build a tree with dangling branches.
+    pub fn from_bytes(bytes: &[u8], len: usize) -> Result<Self, DecodeError> {
+        if bytes.len() != MAX_SYMBOLS {
+            return Err(DecodeError::Truncated);
+        }
+
+        let mut lengths = [0u8; MAX_SYMBOLS];
+        lengths.copy_from_slice(bytes);
+
+        if len > 0 && lengths.iter().all(|&l| l == 0) {
+            return Err(DecodeError::Malformed);
+        }
+        if !lengths_form_complete_tree(&lengths) {
+            return Err(DecodeError::Malformed);
+        }
+
+        Ok(Self { lengths, len })
+    }
+}
+
+/// Checks that `lengths` could have come from a real Huffman merge: a
+/// lone symbol needs no such check, since `decode_bits`'s single-symbol
+/// shortcut never consults its length. Otherwise every complete binary
+/// tree with two or more leaves uses up the codespace exactly (Kraft's
+/// inequality holds with equality) — no internal node is left with a
+/// missing child, which is what lets `decode_bits` call `.unwrap()` on
+/// `left`/`right` without risk.
+///
+/// Walks lengths in increasing order tracking, level by level, how many
+/// of the previous level's unused branches are still available: each
+/// survivor splits into two children one level down (`left *= 2`), and
+/// `count[len]` of those get claimed by leaves at this length. `left`
+/// going negative means more leaves were claimed than the codespace had
+/// room for (over-subscribed); `left` nonzero once every length has been
+/// visited means some branch was never claimed (incomplete). `left` is
+/// clamped well above any realizable leaf count so the doubling can
+/// never overflow, even across lengths as large as `u8::MAX`.
+fn lengths_form_complete_tree(lengths: &[u8; MAX_SYMBOLS]) -> bool {
+    const CAP: i64 = 1 << 32;
+
+    let mut count = [0i64; u8::MAX as usize + 1];
+    let mut used = 0usize;
+    let mut max_len = 0u8;
+    for &len in lengths.iter() {
+        if len > 0 {
+            count[len as usize] += 1;
+            used += 1;
+            max_len = max_len.max(len);
+        }
+    }
+
+    if used <= 1 {
+        return true;
+    }
+
+    let mut left: i64 = 1;
+    for len in 1..=max_len {
+        left = (left * 2).min(CAP);
+        left -= count[len as usize];
+        if left < 0 {
+            return false;
+        }
+    }
+
+    left == 0
+}
+
+/// Errors that can occur while parsing a compressed blob produced by
+/// `compress_to_bytes` back into its original bytes.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// The input is shorter than its format requires: the blob is shorter
+    /// than a header plus an original-length field, a header is not
+    /// exactly one byte per symbol, or the payload runs out of bits before
+    /// the declared number of bytes has been decoded.
+    Truncated,
+    /// The header's code-length table cannot describe the payload: it
+    /// declares no symbols at all while the blob claims a nonzero decoded
+    /// length, or its lengths do not form a complete prefix code.
+    Malformed,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "compressed data is truncated"),
+            DecodeError::Malformed => write!(f, "compressed data has an invalid header"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Encodes `input` as a Huffman bit stream. Returns the `Header` needed
+/// to decode the stream alongside the packed code bits.
+///
+/// Empty input is a valid thing to compress (e.g. an empty file) and is
+/// special-cased the same way the single-symbol case is: there are no
+/// bytes to build a tree out of, so the header just records a length of
+/// zero and an all-unused code-length table.
+fn encode_bits(input: &[u8]) -> (Header, BitVec) {
+    if input.is_empty() {
+        return (Header { lengths: [0u8; MAX_SYMBOLS], len: 0 }, BitVec::new());
+    }
+
+    let freq = get_frequencies(input);
+    let (nodes, root) = build_huffman_tree(&freq)
+        .expect("non-empty input always yields at least one byte frequency");
+    let lengths = code_lengths(&nodes, root);
+    let table = canonical_codes(&lengths);
+
+    let mut bits = BitVec::new();
+    for &b in input {
+        bits.extend(table[&b].iter());
+    }
+
+    (Header { lengths, len: input.len() }, bits)
+}
+
+/// Decodes `bits` back into bytes using `header`, descending left on `0`
+/// and right on `1` from the canonical tree's root and restarting at the
+/// root each time a leaf is reached. Stops after `header.len` bytes have
+/// been emitted, so trailing padding bits in the final byte of a packed
+/// stream are never mistaken for extra output.
+///
+/// Returns `DecodeError::Truncated` if `bits` runs out before `header.len`
+/// bytes have been produced, rather than silently returning a short
+/// vector: callers that trust `header`/`bits` to match (`decode`,
+/// `decompress`) can `.expect()` this away, but `decompress_from_bytes`
+/// needs it to fail safe on a payload truncated by a corrupted blob.
+fn decode_bits(header: &Header, bits: &BitVec) -> Result<Vec<u8>, DecodeError> {
+    let (nodes, root) = tree_from_codes(&canonical_codes(&header.lengths));
+    let mut out = Vec::with_capacity(header.len);
+
+    if header.len == 0 {
+        return Ok(out);
+    }
+
+    if let Some(b) = nodes[root].symbol {
+        out.extend(std::iter::repeat_n(b, header.len));
+        return Ok(out);
+    }
+
+    let mut i = root;
+    for bit in bits.iter() {
+        let node = &nodes[i];
+        i = if bit { node.right.unwrap() } else { node.left.unwrap() };
+
+        if let Some(b) = nodes[i].symbol {
+            out.push(b);
+            if out.len() == header.len {
+                return Ok(out);
+            }
+            i = root;
+        }
+    }
+
+    Err(DecodeError::Truncated)
+}
+
+/// Huffman-compresses arbitrary bytes. Returns the `Header` needed to
+/// decompress alongside the bit-packed payload.
+pub fn compress(input: &[u8]) -> (Header, Vec<u8>) {
+    let (header, bits) = encode_bits(input);
+    (header, bits.to_bytes())
+}
+
+/// Reverses `compress`, unpacking `bytes` into a bit stream and decoding
+/// it against `header`.
+pub fn decompress(header: &Header, bytes: &[u8]) -> Vec<u8> {
+    let bits = BitVec::from_bytes(bytes);
+    decode_bits(header, &bits).expect("header and bytes must come from the same compress() call")
+}
+
+/// Writes a self-contained compressed blob: the serialized `Header`,
+/// the original byte length, and the packed payload bits, in that
+/// order, so `decompress_from_bytes` needs nothing but the bytes
+/// produced here to reconstruct `input`.
+pub fn compress_to_bytes(input: &[u8]) -> Vec<u8> {
+    let (header, payload) = compress(input);
+
+    let mut out = header.to_bytes();
+    out.extend_from_slice(&(input.len() as u64).to_le_bytes());
+    out.extend_from_slice(&payload);
+    out
+}
+
+/// Reverses `compress_to_bytes`.
+///
+/// Before decoding, checks that `payload` actually has enough bits to
+/// supply `len` symbols, given the shortest code length in the header
+/// (every symbol needs at least that many bits): a blob with a declared
+/// length larger than its payload could ever encode is rejected here
+/// rather than handed to `decode_bits`, which would otherwise try to
+/// `Vec::with_capacity` an attacker-controlled `len` and abort the
+/// process instead of returning an error.
+pub fn decompress_from_bytes(bytes: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    if bytes.len() < MAX_SYMBOLS + 8 {
+        return Err(DecodeError::Truncated);
+    }
+
+    let (header_bytes, rest) = bytes.split_at(MAX_SYMBOLS);
+    let (len_bytes, payload) = rest.split_at(8);
+
+    let len = u64::from_le_bytes(len_bytes.try_into().unwrap()) as usize;
+    let header = Header::from_bytes(header_bytes, len)?;
+
+    if let Some(min_len) = header.lengths.iter().copied().filter(|&l| l > 0).min() {
+        if payload.len() * 8 < len.saturating_mul(min_len as usize) {
+            return Err(DecodeError::Truncated);
+        }
+    }
+
+    let bits = BitVec::from_bytes(payload);
+    decode_bits(&header, &bits)
+}
+
+/// Encodes `s` as a Huffman bit stream. A thin wrapper around the
+/// byte-oriented encoder: `s` is encoded to UTF-8 bytes first, since the
+/// tree and code table only ever operate over `u8` symbols.
+pub fn encode(s: &str) -> (Header, BitVec) {
+    encode_bits(s.as_bytes())
+}
+
+/// Reverses `encode`. A thin wrapper around the byte-oriented decoder:
+/// the decoded bytes are parsed back as UTF-8.
+pub fn decode(header: &Header, bits: &BitVec) -> String {
+    let bytes = decode_bits(header, bits)
+        .expect("header and bits must come from the same encode() call");
+    String::from_utf8(bytes).expect("decoded bytes are not valid UTF-8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tree_built_correctly() {
+        let s = "aaaabbbccd";
+        let freq = get_frequencies(s.as_bytes());
+        assert_eq!(freq[b'a' as usize], 4);
+        assert_eq!(freq[b'b' as usize], 3);
+        assert_eq!(freq[b'c' as usize], 2);
+        assert_eq!(freq[b'd' as usize], 1);
+
+        let (nodes, root) = build_huffman_tree(&freq).unwrap();
+        assert_eq!(nodes.len(), 7);
+
+        let left = nodes[root].left.unwrap();
+        let right = nodes[root].right.unwrap();
+        assert_eq!(nodes[left].symbol, Some(b'a'));
+
+        let right_left = nodes[right].left.unwrap();
+        let right_right = nodes[right].right.unwrap();
+        assert_eq!(nodes[right_left].symbol, Some(b'b'));
+
+        let leaf_d = nodes[right_right].left.unwrap();
+        let leaf_c = nodes[right_right].right.unwrap();
+        assert_eq!(nodes[leaf_d].symbol, Some(b'd'));
+        assert_eq!(nodes[leaf_c].symbol, Some(b'c'));
+
+        let lengths = code_lengths(&nodes, root);
+        assert_eq!(lengths[b'a' as usize], 1);
+        assert_eq!(lengths[b'b' as usize], 2);
+        assert_eq!(lengths[b'c' as usize], 3);
+        assert_eq!(lengths[b'd' as usize], 3);
+    }
+
+    #[test]
+    fn canonical_codes_ordered_by_length_then_symbol() {
+        let mut lengths = [0u8; MAX_SYMBOLS];
+        lengths[b'a' as usize] = 1;
+        lengths[b'b' as usize] = 2;
+        lengths[b'c' as usize] = 3;
+        lengths[b'd' as usize] = 3;
+
+        let table = canonical_codes(&lengths);
+        let bits_of = |b: u8| -> Vec<bool> { table[&b].iter().collect() };
+
+        assert_eq!(bits_of(b'a'), vec![false]);
+        assert_eq!(bits_of(b'b'), vec![true, false]);
+        assert_eq!(bits_of(b'c'), vec![true, true, false]);
+        assert_eq!(bits_of(b'd'), vec![true, true, true]);
+    }
+
+    #[test]
+    fn canonical_codes_handle_lengths_past_32_bits() {
+        let mut lengths = [0u8; MAX_SYMBOLS];
+        lengths[b'a' as usize] = 40;
+        lengths[b'b' as usize] = 40;
+
+        let table = canonical_codes(&lengths);
+        assert_eq!(table[&b'a'].len(), 40);
+        assert_eq!(table[&b'b'].len(), 40);
+        assert_ne!(table[&b'a'], table[&b'b']);
+    }
+
+    #[test]
+    fn encode_is_deterministic_across_runs() {
+        let s = "aaaabbbccd";
+        let (header1, bits1) = encode(s);
+        let (header2, bits2) = encode(s);
+        assert_eq!(bits1, bits2);
+        assert_eq!(header1.lengths.to_vec(), header2.lengths.to_vec());
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let s = "aaaabbbccd";
+        let (header, bits) = encode(s);
+        assert_eq!(decode(&header, &bits), s);
+    }
+
+    #[test]
+    fn empty_input_round_trips_without_panicking() {
+        let (header, bits) = encode("");
+        assert_eq!(bits.len(), 0);
+        assert_eq!(decode(&header, &bits), "");
+
+        let (header, packed) = compress(&[]);
+        assert_eq!(decompress(&header, &packed), Vec::<u8>::new());
+
+        let blob = compress_to_bytes(&[]);
+        assert_eq!(decompress_from_bytes(&blob).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn encode_decode_single_symbol() {
+        let s = "aaaa";
+        let (header, bits) = encode(s);
+        assert_eq!(bits.len(), s.len());
+        assert!(bits.iter().all(|b| !b));
+        assert_eq!(decode(&header, &bits), s);
+    }
+
+    #[test]
+    fn compress_decompress_round_trip_arbitrary_bytes() {
+        let input: Vec<u8> = vec![0, 1, 1, 2, 2, 2, 255, 255, 255, 255, 10];
+        let (header, packed) = compress(&input);
+        assert_eq!(decompress(&header, &packed), input);
+    }
+
+    #[test]
+    fn compress_to_bytes_round_trip_is_self_contained() {
+        let input: Vec<u8> = vec![0, 1, 1, 2, 2, 2, 255, 255, 255, 255, 10];
+        let blob = compress_to_bytes(&input);
+        assert_eq!(decompress_from_bytes(&blob).unwrap(), input);
+    }
+
+    #[test]
+    fn decompress_from_bytes_rejects_truncated_input() {
+        assert!(matches!(decompress_from_bytes(&[0u8; 10]), Err(DecodeError::Truncated)));
+    }
+
+    #[test]
+    fn decompress_from_bytes_rejects_empty_header_with_nonzero_length() {
+        let mut blob = vec![0u8; MAX_SYMBOLS];
+        blob.extend_from_slice(&1u64.to_le_bytes());
+        blob.push(0);
+        assert!(matches!(decompress_from_bytes(&blob), Err(DecodeError::Malformed)));
+    }
+
+    #[test]
+    fn decompress_from_bytes_rejects_payload_truncated_after_header() {
+        let input: Vec<u8> = vec![0, 1, 1, 2, 2, 2, 255, 255, 255, 255, 10];
+        let mut blob = compress_to_bytes(&input);
+        blob.truncate(blob.len() - 2);
+        assert!(matches!(decompress_from_bytes(&blob), Err(DecodeError::Truncated)));
+    }
+
+    #[test]
+    fn decompress_from_bytes_rejects_declared_length_too_large_for_payload() {
+        let input: Vec<u8> = vec![0, 1, 1, 2, 2, 2, 255, 255, 255, 255, 10];
+        let mut blob = compress_to_bytes(&input);
+        let huge_len = (u64::MAX / 2).to_le_bytes();
+        blob[MAX_SYMBOLS..MAX_SYMBOLS + 8].copy_from_slice(&huge_len);
+        assert!(matches!(decompress_from_bytes(&blob), Err(DecodeError::Truncated)));
+    }
+
+    #[test]
+    fn lengths_form_complete_tree_accepts_real_output() {
+        let (header, _) = encode("aaaabbbccd");
+        assert!(lengths_form_complete_tree(&header.lengths));
+    }
+
+    #[test]
+    fn lengths_form_complete_tree_rejects_over_subscribed_lengths() {
+        let mut lengths = [0u8; MAX_SYMBOLS];
+        lengths[b'a' as usize] = 1;
+        lengths[b'b' as usize] = 1;
+        lengths[b'c' as usize] = 1;
+        assert!(!lengths_form_complete_tree(&lengths));
+    }
+
+    #[test]
+    fn lengths_form_complete_tree_rejects_incomplete_lengths() {
+        let mut lengths = [0u8; MAX_SYMBOLS];
+        lengths[b'a' as usize] = 1;
+        lengths[b'b' as usize] = 3;
+        assert!(!lengths_form_complete_tree(&lengths));
+    }
+
+    #[test]
+    fn header_from_bytes_rejects_a_single_corrupted_length() {
+        let (header, _) = encode("aaaabbbccd");
+        let mut bytes = header.to_bytes();
+        bytes[b'd' as usize] = 200;
+        assert!(matches!(Header::from_bytes(&bytes, 10), Err(DecodeError::Malformed)));
+    }
+}